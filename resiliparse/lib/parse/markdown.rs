@@ -0,0 +1,340 @@
+// Copyright 2023 Janek Bevendorff
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! HTML-to-Markdown rendering of a DOM subtree.
+//!
+//! The conversion runs in two passes, mirroring pulldown-cmark's model: the
+//! first walks the DOM into a flat [`Event`] stream tagged with block/inline
+//! semantics, the second renders that stream to a string while tracking list
+//! nesting and blank-line separation.
+
+use crate::parse::html::{DOMNode, NodeType};
+
+/// A semantic event in the flattened document stream.
+enum Event {
+    Start(Tag),
+    End(Tag),
+    Text(String),
+    SoftBreak,
+}
+
+/// Block- and inline-level constructs recognised during the DOM walk.
+#[derive(Clone, PartialEq, Eq)]
+enum Tag {
+    Paragraph,
+    Heading(u8),
+    BlockQuote,
+    CodeBlock,
+    List { ordered: bool, loose: bool },
+    Item,
+    Emphasis,
+    Strong,
+    CodeInline,
+    Link(String),
+    Image { src: String, alt: String },
+}
+
+/// Render a DOM subtree to CommonMark.
+pub fn to_markdown(root: &DOMNode) -> Option<String> {
+    let mut events = Vec::new();
+    build_events(root, &mut events);
+    Some(render(&events))
+}
+
+/// First pass: flatten the DOM rooted at `node` into events.
+fn build_events(node: &DOMNode, out: &mut Vec<Event>) {
+    match node.node_type() {
+        NodeType::Text => {
+            if let Some(v) = node.value() {
+                out.push(Event::Text(v));
+            }
+        }
+        NodeType::Element => {
+            let tag = node.tag().unwrap_or_default().to_ascii_lowercase();
+            match tag.as_str() {
+                "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+                    let level = tag.as_bytes()[1] - b'0';
+                    wrap(node, out, Tag::Heading(level));
+                }
+                "p" => wrap(node, out, Tag::Paragraph),
+                "blockquote" => wrap(node, out, Tag::BlockQuote),
+                "ul" => wrap(node, out, Tag::List { ordered: false, loose: list_is_loose(node) }),
+                "ol" => wrap(node, out, Tag::List { ordered: true, loose: list_is_loose(node) }),
+                "li" => wrap(node, out, Tag::Item),
+                "strong" | "b" => wrap(node, out, Tag::Strong),
+                "em" | "i" => wrap(node, out, Tag::Emphasis),
+                "a" => {
+                    let href = node.get_attribute("href").unwrap_or_default();
+                    wrap(node, out, Tag::Link(href));
+                }
+                "img" => {
+                    let src = node.get_attribute("src").unwrap_or_default();
+                    let alt = node.get_attribute("alt").unwrap_or_default();
+                    out.push(Event::Start(Tag::Image { src: src.clone(), alt: alt.clone() }));
+                    out.push(Event::End(Tag::Image { src, alt }));
+                }
+                "code" => wrap(node, out, Tag::CodeInline),
+                "pre" => {
+                    // Preformatted text is emitted verbatim as a fenced block.
+                    out.push(Event::Start(Tag::CodeBlock));
+                    out.push(Event::Text(node.inner_text().unwrap_or_default()));
+                    out.push(Event::End(Tag::CodeBlock));
+                }
+                "br" => out.push(Event::SoftBreak),
+                // Unknown elements are transparent: recurse into their children.
+                _ => {
+                    for child in node.child_nodes() {
+                        build_events(&child, out);
+                    }
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+/// A list is *loose* (blank-line-separated items) when any of its items holds
+/// a block-level child; otherwise it renders tight, like the HTML it came from.
+fn list_is_loose(list: &DOMNode) -> bool {
+    for item in list.child_nodes() {
+        if item.node_type() != NodeType::Element
+            || item.tag().unwrap_or_default().to_ascii_lowercase() != "li" {
+            continue;
+        }
+        for child in item.child_nodes() {
+            if child.node_type() == NodeType::Element && matches!(
+                child.tag().unwrap_or_default().to_ascii_lowercase().as_str(),
+                "p" | "div" | "ul" | "ol" | "blockquote" | "pre"
+                    | "h1" | "h2" | "h3" | "h4" | "h5" | "h6") {
+                return true;
+            }
+        }
+    }
+    false
+}
+
+/// Emit `Start(tag)`, the node's children, then `End(tag)`.
+fn wrap(node: &DOMNode, out: &mut Vec<Event>, tag: Tag) {
+    out.push(Event::Start(tag.clone()));
+    for child in node.child_nodes() {
+        build_events(&child, out);
+    }
+    out.push(Event::End(tag));
+}
+
+/// Second pass: render the event stream to a Markdown string.
+fn render(events: &[Event]) -> String {
+    let mut out = String::new();
+    let mut list_stack: Vec<ListState> = Vec::new();
+    let mut quote_depth: usize = 0;
+    let mut in_code_block = false;
+    let mut inline = String::new();
+    let mut pending_blank = false;
+
+    // Flush accumulated inline text as a block with the current prefixes.
+    macro_rules! flush_block {
+        () => {
+            if !inline.is_empty() {
+                if pending_blank && !out.is_empty() {
+                    out.push('\n');
+                }
+                let prefix = block_prefix(&list_stack, quote_depth);
+                for (i, line) in inline.split('\n').enumerate() {
+                    if i > 0 {
+                        out.push('\n');
+                    }
+                    out.push_str(&prefix);
+                    out.push_str(line);
+                }
+                out.push('\n');
+                pending_blank = true;
+                inline.clear();
+            }
+        };
+    }
+
+    for event in events {
+        match event {
+            Event::Text(t) => {
+                if in_code_block {
+                    inline.push_str(t);
+                } else {
+                    let collapsed = collapse_ws(t);
+                    if collapsed.trim().is_empty() {
+                        // Whitespace-only text (indentation between block tags)
+                        // is significant only as a separator between inline runs,
+                        // never as a block of its own.
+                        if !inline.is_empty()
+                            && !inline.ends_with(|c: char| c.is_whitespace()) {
+                            inline.push(' ');
+                        }
+                    } else {
+                        inline.push_str(&escape_inline(&collapsed));
+                    }
+                }
+            }
+            Event::SoftBreak => inline.push('\n'),
+            Event::Start(tag) => match tag {
+                Tag::Heading(level) => {
+                    flush_block!();
+                    inline.push_str(&"#".repeat(*level as usize));
+                    inline.push(' ');
+                }
+                Tag::BlockQuote => {
+                    flush_block!();
+                    quote_depth += 1;
+                }
+                Tag::List { ordered, loose } => {
+                    flush_block!();
+                    list_stack.push(ListState {
+                        ordered: *ordered, loose: *loose, counter: 1, seen_item: false,
+                    });
+                }
+                Tag::Item => {
+                    flush_block!();
+                    if let Some(state) = list_stack.last_mut() {
+                        // Tight lists keep consecutive items on adjacent lines;
+                        // only loose lists get a blank line between them.
+                        if state.seen_item && !state.loose {
+                            pending_blank = false;
+                        }
+                        state.seen_item = true;
+                        if state.ordered {
+                            inline.push_str(&format!("{}. ", state.counter));
+                            state.counter += 1;
+                        } else {
+                            inline.push_str("- ");
+                        }
+                    }
+                }
+                Tag::CodeBlock => {
+                    flush_block!();
+                    in_code_block = true;
+                    inline.push_str("```\n");
+                }
+                Tag::Strong => inline.push_str("**"),
+                Tag::Emphasis => inline.push('_'),
+                Tag::CodeInline => inline.push('`'),
+                Tag::Link(_) => inline.push('['),
+                Tag::Image { src, alt } => {
+                    inline.push_str(&format!("![{}]({})", alt, src));
+                }
+                Tag::Paragraph => flush_block!(),
+            },
+            Event::End(tag) => match tag {
+                Tag::Heading(_) | Tag::Paragraph => flush_block!(),
+                Tag::BlockQuote => {
+                    flush_block!();
+                    quote_depth = quote_depth.saturating_sub(1);
+                }
+                Tag::List { .. } => {
+                    flush_block!();
+                    list_stack.pop();
+                }
+                Tag::Item => flush_block!(),
+                Tag::CodeBlock => {
+                    inline.push_str("\n```");
+                    flush_block!();
+                    in_code_block = false;
+                }
+                Tag::Strong => inline.push_str("**"),
+                Tag::Emphasis => inline.push('_'),
+                Tag::CodeInline => inline.push('`'),
+                Tag::Link(href) => inline.push_str(&format!("]({})", href)),
+                Tag::Image { .. } => {}
+            },
+        }
+    }
+    flush_block!();
+
+    out.trim_end().to_string()
+}
+
+/// Per-list rendering state.
+struct ListState {
+    ordered: bool,
+    loose: bool,
+    counter: usize,
+    seen_item: bool,
+}
+
+/// Line prefix for the current blockquote and list nesting.
+fn block_prefix(list_stack: &[ListState], quote_depth: usize) -> String {
+    let mut prefix = "> ".repeat(quote_depth);
+    // Nested list items are indented two spaces per ancestor list.
+    if list_stack.len() > 1 {
+        prefix.push_str(&"  ".repeat(list_stack.len() - 1));
+    }
+    prefix
+}
+
+/// Collapse runs of ASCII whitespace to single spaces.
+fn collapse_ws(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut last_ws = false;
+    for c in text.chars() {
+        if c.is_ascii_whitespace() {
+            if !last_ws {
+                out.push(' ');
+            }
+            last_ws = true;
+        } else {
+            out.push(c);
+            last_ws = false;
+        }
+    }
+    out
+}
+
+/// Escape Markdown-significant characters in inline text runs.
+fn escape_inline(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        if matches!(c, '*' | '_' | '`' | '[' | ']') {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse::html::HTMLTree;
+
+    #[test]
+    fn renders_headings_and_paragraphs() {
+        let tree = HTMLTree::from("<body><h1>Title</h1><p>Hello <strong>world</strong></p></body>");
+        let md = tree.body().unwrap().to_markdown().unwrap();
+        assert_eq!(md, "# Title\n\nHello **world**");
+    }
+
+    #[test]
+    fn renders_links_and_lists() {
+        let tree = HTMLTree::from("<body><ul><li>a</li><li><a href=\"u\">b</a></li></ul></body>");
+        let md = tree.body().unwrap().to_markdown().unwrap();
+        assert!(md.contains("- a"));
+        assert!(md.contains("- [b](u)"));
+    }
+
+    #[test]
+    fn indented_markup_stays_tight() {
+        let tree = HTMLTree::from(
+            "<body>\n  <p>Hi</p>\n  <ul>\n    <li>a</li>\n    <li>b</li>\n  </ul>\n</body>");
+        let md = tree.body().unwrap().to_markdown().unwrap();
+        // No stray whitespace-only blocks, and tight list items are adjacent.
+        assert_eq!(md, "Hi\n\n- a\n- b");
+    }
+}
@@ -0,0 +1,219 @@
+// Copyright 2023 Janek Bevendorff
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Allowlist-based HTML sanitizer built on top of the DOM parse path.
+
+use std::collections::{HashMap, HashSet};
+
+use crate::parse::html::{DOMNode, HTMLTree, NodeType};
+
+/// How to treat an element whose tag is not on the allowlist.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum DisallowedMode {
+    /// Drop the element but reparent its children in its place.
+    Unwrap,
+    /// Delete the element and its entire subtree.
+    Drop,
+}
+
+/// Sanitization policy: what survives the DOM walk.
+pub struct SanitizePolicy {
+    /// Lowercase tag names that are allowed to remain.
+    pub allowed_tags: HashSet<String>,
+    /// Per-tag set of lowercase attribute names that are allowed to remain.
+    pub allowed_attributes: HashMap<String, HashSet<String>>,
+    /// Lowercase URL schemes permitted in `href`/`src` values.
+    pub allowed_schemes: HashSet<String>,
+    /// Treatment of disallowed elements.
+    pub disallowed_mode: DisallowedMode,
+    /// Remove comment nodes.
+    pub strip_comments: bool,
+    /// Remove processing-instruction nodes.
+    pub strip_processing_instructions: bool,
+}
+
+impl Default for SanitizePolicy {
+    /// A conservative policy allowing common formatting and link markup.
+    fn default() -> Self {
+        let allowed_tags = [
+            "a", "b", "blockquote", "br", "code", "em", "i", "li", "ol", "p",
+            "pre", "span", "strong", "ul",
+        ].iter().map(|s| s.to_string()).collect();
+
+        let mut allowed_attributes = HashMap::new();
+        allowed_attributes.insert("a".to_string(),
+            ["href", "title"].iter().map(|s| s.to_string()).collect());
+
+        let allowed_schemes = ["http", "https", "mailto"]
+            .iter().map(|s| s.to_string()).collect();
+
+        Self {
+            allowed_tags,
+            allowed_attributes,
+            allowed_schemes,
+            disallowed_mode: DisallowedMode::Unwrap,
+            strip_comments: true,
+            strip_processing_instructions: true,
+        }
+    }
+}
+
+/// Sanitize a parsed [`HTMLTree`] in place against `policy` and return the
+/// cleaned markup of its body.
+pub fn sanitize(tree: &HTMLTree, policy: &SanitizePolicy) -> Option<String> {
+    let root = tree.body().or_else(|| tree.document())?;
+    for child in root.child_nodes() {
+        clean_node(&child, policy, &root);
+    }
+    root.inner_html()
+}
+
+/// Parse a fragment of bytes, sanitize it, and return the cleaned markup.
+#[inline]
+pub fn sanitize_fragment(bytes: &[u8], policy: &SanitizePolicy) -> Option<String> {
+    sanitize(&HTMLTree::from(bytes), policy)
+}
+
+/// Recursively clean `node`, reparenting into `parent` when unwrapping.
+fn clean_node(node: &DOMNode, policy: &SanitizePolicy, parent: &DOMNode) {
+    match node.node_type() {
+        NodeType::Element => {
+            let tag = node.tag().unwrap_or_default().to_ascii_lowercase();
+            // Elements whose text content is script/markup rather than prose
+            // are always dropped whole; unwrapping them would reparent their
+            // raw contents (e.g. `evil()`) into the output as body text.
+            if is_unsafe_container(&tag) {
+                node.remove();
+                return;
+            }
+            if !policy.allowed_tags.contains(&tag) {
+                match policy.disallowed_mode {
+                    DisallowedMode::Drop => node.remove(),
+                    DisallowedMode::Unwrap => {
+                        for child in node.child_nodes() {
+                            clean_node(&child, policy, node);
+                        }
+                        for child in node.child_nodes() {
+                            parent.insert_before(&child, node);
+                        }
+                        node.remove();
+                    }
+                }
+                return;
+            }
+
+            let allowed = policy.allowed_attributes.get(&tag);
+            for (name, value) in node.attributes() {
+                let lname = name.to_ascii_lowercase();
+                if !allowed.map_or(false, |s| s.contains(&lname)) {
+                    node.remove_attribute(&name);
+                    continue;
+                }
+                if matches!(lname.as_str(), "href" | "src")
+                    && !scheme_allowed(&value, &policy.allowed_schemes) {
+                    node.remove_attribute(&name);
+                }
+            }
+
+            for child in node.child_nodes() {
+                clean_node(&child, policy, node);
+            }
+        }
+        NodeType::Comment if policy.strip_comments => node.remove(),
+        NodeType::ProcessingInstruction if policy.strip_processing_instructions => node.remove(),
+        _ => {}
+    }
+}
+
+/// Tags whose children are script or raw markup, never displayable prose; their
+/// subtree is deleted outright regardless of [`DisallowedMode`].
+fn is_unsafe_container(tag: &str) -> bool {
+    matches!(tag, "script" | "style" | "template" | "noscript")
+}
+
+/// Whether a URL's scheme is permitted. Relative URLs (no scheme) are allowed.
+///
+/// Browsers strip leading/trailing whitespace and all C0 control characters
+/// before dispatching a URL, so `" javascript:…"` and `"java\tscript:…"` still
+/// execute. We mirror that: trim ASCII whitespace, and reject outright any
+/// value containing C0 control characters rather than mistaking it for a
+/// scheme-less relative URL.
+fn scheme_allowed(url: &str, allowed: &HashSet<String>) -> bool {
+    let trimmed = url.trim_matches(|c: char| c.is_ascii_whitespace());
+    if trimmed.bytes().any(|b| b < 0x20 || b == 0x7f) {
+        return false;
+    }
+    match split_scheme(trimmed) {
+        Some(scheme) => allowed.contains(&scheme.to_ascii_lowercase()),
+        None => true,
+    }
+}
+
+/// Extract the scheme prefix of a URL (the part before the first `:`), if the
+/// prefix is a syntactically valid scheme per RFC 3986.
+fn split_scheme(url: &str) -> Option<&str> {
+    let end = url.find(':')?;
+    let scheme = &url[..end];
+    let mut chars = scheme.chars();
+    let first = chars.next()?;
+    if !first.is_ascii_alphabetic() {
+        return None;
+    }
+    if chars.all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.')) {
+        Some(scheme)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse::html::HTMLTree;
+
+    #[test]
+    fn drops_disallowed_and_unwraps() {
+        let policy = SanitizePolicy::default();
+        let html = r#"<div><script>evil()</script><p onclick="x">hi <b>bold</b></p><!-- c --></div>"#;
+        let tree = HTMLTree::from(html);
+        let out = sanitize(&tree, &policy).unwrap();
+        assert!(out.contains("<p>hi <b>bold</b></p>"));
+        assert!(!out.contains("onclick"));
+        assert!(!out.contains("script"));
+        // The script's text content must not leak into the body.
+        assert!(!out.contains("evil()"));
+        assert!(!out.contains("<!--"));
+    }
+
+    #[test]
+    fn strips_dangerous_url_schemes() {
+        let policy = SanitizePolicy::default();
+        let html = r#"<a href="javascript:alert(1)">x</a><a href="https://ok">y</a>"#;
+        let out = sanitize(&HTMLTree::from(html), &policy).unwrap();
+        assert!(!out.contains("javascript"));
+        assert!(out.contains("https://ok"));
+    }
+
+    #[test]
+    fn strips_obfuscated_url_schemes() {
+        let policy = SanitizePolicy::default();
+        let html = concat!(
+            r#"<a href=" javascript:alert(1)">x</a>"#,
+            "<a href=\"java\tscript:alert(1)\">y</a>",
+        );
+        let out = sanitize(&HTMLTree::from(html), &policy).unwrap();
+        assert!(!out.contains("javascript"));
+        assert!(!out.contains("script"));
+    }
+}
@@ -0,0 +1,151 @@
+// Copyright 2023 Janek Bevendorff
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Streaming SAX-style tokenizer built on lexbor's tokenizer layer.
+//!
+//! Unlike the DOM parse path, this drives a user-supplied [`TokenHandler`]
+//! with borrowed `&str` slices of lexbor's token data, so multi-megabyte
+//! documents can be processed in a single forward pass without materializing
+//! a tree or allocating owned copies of every token.
+
+use std::ffi::c_void;
+use std::slice;
+
+use crate::third_party::lexbor::*;
+
+/// Handler invoked once per token during a tokenizer pass.
+///
+/// All methods default to no-ops so implementors override only the events
+/// they care about. The borrowed slices are valid only for the duration of
+/// the call.
+pub trait TokenHandler {
+    fn on_open_tag(&mut self, _name: &str, _attrs: &[(&str, &str)]) {}
+    fn on_close_tag(&mut self, _name: &str) {}
+    fn on_text(&mut self, _text: &str) {}
+    fn on_comment(&mut self, _text: &str) {}
+    fn on_doctype(&mut self, _name: &str) {}
+}
+
+/// Push/event HTML tokenizer.
+pub struct HTMLTokenizer;
+
+impl HTMLTokenizer {
+    /// Tokenize `bytes`, dispatching each token to `handler`.
+    ///
+    /// The bytes must be valid UTF-8; transcode up front with
+    /// [`HTMLTree::parse_with_encoding`](crate::parse::html::HTMLTree::parse_with_encoding)
+    /// equivalents for non-UTF-8 input. Returns `false` if the tokenizer
+    /// could not be initialised.
+    pub fn tokenize<H: TokenHandler>(bytes: &[u8], handler: &mut H) -> bool {
+        unsafe {
+            let tkz = lxb_html_tokenizer_create();
+            if lxb_html_tokenizer_init(tkz) != LXB_STATUS_OK {
+                lxb_html_tokenizer_destroy(tkz);
+                return false;
+            }
+            lxb_html_tokenizer_callback_token_done_set(
+                tkz, Some(token_done::<H>), handler as *mut H as *mut c_void);
+            lxb_html_tokenizer_begin(tkz);
+            lxb_html_tokenizer_chunk(tkz, bytes.as_ptr(), bytes.len());
+            lxb_html_tokenizer_end(tkz);
+            lxb_html_tokenizer_destroy(tkz);
+        }
+        true
+    }
+}
+
+/// Borrow a `&str` slice from a pair of `lxb_char_t` begin/end pointers.
+unsafe fn span<'a>(begin: *const lxb_char_t, end: *const lxb_char_t) -> &'a str {
+    if begin.is_null() || end.is_null() || end < begin {
+        return "";
+    }
+    let len = end.offset_from(begin) as usize;
+    std::str::from_utf8_unchecked(slice::from_raw_parts(begin.cast(), len))
+}
+
+/// Tokenizer callback bridging lexbor tokens to a [`TokenHandler`].
+unsafe extern "C" fn token_done<H: TokenHandler>(
+    tkz: *mut lxb_html_tokenizer_t,
+    token: *mut lxb_html_token_t,
+    ctx: *mut c_void,
+) -> *mut lxb_html_token_t {
+    let handler = &mut *(ctx as *mut H);
+    let tag_id = (*token).tag_id;
+
+    match tag_id {
+        LXB_TAG__TEXT => handler.on_text(span((*token).text_start, (*token).text_end)),
+        LXB_TAG__EM_COMMENT => handler.on_comment(span((*token).text_start, (*token).text_end)),
+        LXB_TAG__EM_DOCTYPE => handler.on_doctype(span((*token).text_start, (*token).text_end)),
+        LXB_TAG__END_OF_FILE => {}
+        _ => {
+            let mut name_len = 0;
+            let name_ptr = lxb_tag_name_by_id(
+                lxb_html_tokenizer_tags_noi(tkz), tag_id, &mut name_len);
+            let name = std::str::from_utf8_unchecked(
+                slice::from_raw_parts(name_ptr.cast(), name_len));
+
+            if (*token).type_ & LXB_HTML_TOKEN_TYPE_CLOSE != 0 {
+                handler.on_close_tag(name);
+            } else {
+                let mut attrs: Vec<(&str, &str)> = Vec::new();
+                let mut attr = (*token).attr_first;
+                while !attr.is_null() {
+                    let name = span((*attr).name_begin, (*attr).name_end);
+                    let value = span((*attr).value_begin, (*attr).value_end);
+                    attrs.push((name, value));
+                    attr = (*attr).next;
+                }
+                handler.on_open_tag(name, &attrs);
+            }
+        }
+    }
+
+    token
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Default)]
+    struct Collector {
+        open: Vec<String>,
+        close: usize,
+        hrefs: Vec<String>,
+    }
+
+    impl TokenHandler for Collector {
+        fn on_open_tag(&mut self, name: &str, attrs: &[(&str, &str)]) {
+            self.open.push(name.to_string());
+            for (k, v) in attrs {
+                if *k == "href" {
+                    self.hrefs.push(v.to_string());
+                }
+            }
+        }
+        fn on_close_tag(&mut self, _name: &str) {
+            self.close += 1;
+        }
+    }
+
+    #[test]
+    fn drives_handler_events() {
+        let mut c = Collector::default();
+        assert!(HTMLTokenizer::tokenize(
+            b"<p>hi <a href=\"u\">x</a></p>", &mut c));
+        assert!(c.open.iter().any(|t| t == "a"));
+        assert_eq!(c.hrefs, vec!["u".to_string()]);
+        assert!(c.close >= 2);
+    }
+}
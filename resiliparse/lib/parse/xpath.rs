@@ -0,0 +1,465 @@
+// Copyright 2023 Janek Bevendorff
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Abbreviated XPath 1.0 evaluation over the lexbor DOM.
+//!
+//! Supports child (`/`) and descendant (`//`) axes, name and wildcard node
+//! tests, the attribute axis (`@attr`), positional predicates (`[n]`), and
+//! attribute/text predicates (`[@class='x']`, `[contains(text(),'y')]`).
+
+use crate::parse::html::{DOMNode, NodeType};
+
+/// Result of evaluating an XPath expression.
+pub enum XPathResult {
+    NodeSet(Vec<DOMNode>),
+    Bool(bool),
+    Number(f64),
+    String(String),
+    /// Attribute-axis result: one value per matched node, in document order.
+    StringSet(Vec<String>),
+}
+
+/// Error raised while parsing or evaluating an XPath expression.
+#[derive(Debug, PartialEq, Eq)]
+pub enum XPathError {
+    /// The expression could not be parsed at the given byte offset.
+    Parse(String),
+    /// The context node has been detached from its tree.
+    Detached,
+}
+
+/// Evaluate `expr` against the subtree rooted at `context`. Absolute paths
+/// (`/…`, `//…`) are evaluated from the document root instead.
+pub fn evaluate(context: &DOMNode, expr: &str) -> Result<XPathResult, XPathError> {
+    let path = Parser::new(expr).parse_path()?;
+    let start = if path.absolute { document_root(context) } else { context.clone() };
+    let mut attr_terminal: Option<Vec<String>> = None;
+    let mut set = vec![start];
+
+    for step in &path.steps {
+        match &step.axis {
+            Axis::Attribute => {
+                // The attribute axis is terminal: collect one value per matched
+                // node in document order.
+                let name = match &step.test {
+                    NodeTest::Name(n) => n.clone(),
+                    NodeTest::Wildcard => String::new(),
+                };
+                attr_terminal = Some(
+                    dedupe(std::mem::take(&mut set))
+                        .iter().filter_map(|n| n.get_attribute(&name)).collect());
+            }
+            axis => {
+                let mut next: Vec<DOMNode> = Vec::new();
+                for node in &set {
+                    let candidates = match axis {
+                        Axis::Child => node.child_nodes(),
+                        Axis::Descendant => descendants(node),
+                        Axis::Attribute => unreachable!(),
+                    };
+                    let matched: Vec<DOMNode> = candidates
+                        .into_iter()
+                        .filter(|cand| test_matches(cand, &step.test))
+                        .collect();
+                    // Positional predicates count within each context node's
+                    // own children, so the descendant axis — which flattens
+                    // every generation into one list — must be regrouped by
+                    // parent before predicates apply.
+                    let groups = match axis {
+                        Axis::Descendant => group_by_parent(matched.clone()),
+                        _ => vec![matched.clone()],
+                    };
+                    let mut kept = std::collections::HashSet::new();
+                    for group in groups {
+                        kept.extend(apply_predicates(group, &step.predicates)
+                            .iter().map(DOMNode::node_id));
+                    }
+                    // Regrouping reorders nodes; emit the survivors in document order.
+                    next.extend(matched.into_iter().filter(|n| kept.contains(&n.node_id())));
+                }
+                set = next;
+            }
+        }
+    }
+
+    if let Some(values) = attr_terminal {
+        return Ok(XPathResult::StringSet(values));
+    }
+    Ok(XPathResult::NodeSet(dedupe(set)))
+}
+
+/// A parsed location path: a sequence of steps.
+struct LocationPath {
+    absolute: bool,
+    steps: Vec<Step>,
+}
+
+struct Step {
+    axis: Axis,
+    test: NodeTest,
+    predicates: Vec<Predicate>,
+}
+
+#[derive(Clone, Copy)]
+enum Axis {
+    Child,
+    Descendant,
+    Attribute,
+}
+
+enum NodeTest {
+    Name(String),
+    Wildcard,
+}
+
+enum Predicate {
+    Position(usize),
+    AttrExists(String),
+    AttrEquals(String, String),
+    ContainsText(String),
+}
+
+/// Recursive-descent parser for the abbreviated XPath subset.
+struct Parser<'a> {
+    input: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn new(input: &'a str) -> Self {
+        Parser { input: input.as_bytes(), pos: 0 }
+    }
+
+    fn err(&self, msg: &str) -> XPathError {
+        XPathError::Parse(format!("{} at offset {}", msg, self.pos))
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.input.get(self.pos).copied()
+    }
+
+    fn bump(&mut self) -> Option<u8> {
+        let c = self.peek();
+        if c.is_some() {
+            self.pos += 1;
+        }
+        c
+    }
+
+    fn parse_path(&mut self) -> Result<LocationPath, XPathError> {
+        let mut absolute = false;
+        // Axis applied to the next step; `//` switches it to descendant.
+        let mut axis = Axis::Child;
+        if self.peek() == Some(b'/') {
+            absolute = true;
+            self.bump();
+            if self.peek() == Some(b'/') {
+                axis = Axis::Descendant;
+                self.bump();
+            }
+        }
+
+        let mut steps = Vec::new();
+        loop {
+            let step = self.parse_step(axis)?;
+            steps.push(step);
+
+            match self.peek() {
+                Some(b'/') => {
+                    self.bump();
+                    if self.peek() == Some(b'/') {
+                        self.bump();
+                        axis = Axis::Descendant;
+                    } else {
+                        axis = Axis::Child;
+                    }
+                }
+                None => break,
+                _ => return Err(self.err("expected '/' or end of expression")),
+            }
+        }
+
+        if steps.is_empty() {
+            return Err(self.err("empty location path"));
+        }
+        Ok(LocationPath { absolute, steps })
+    }
+
+    fn parse_step(&mut self, axis: Axis) -> Result<Step, XPathError> {
+        let axis = if self.peek() == Some(b'@') {
+            self.bump();
+            Axis::Attribute
+        } else {
+            axis
+        };
+
+        let test = if self.peek() == Some(b'*') {
+            self.bump();
+            NodeTest::Wildcard
+        } else {
+            let name = self.parse_name();
+            if name.is_empty() {
+                return Err(self.err("expected node test"));
+            }
+            NodeTest::Name(name)
+        };
+
+        let mut predicates = Vec::new();
+        while self.peek() == Some(b'[') {
+            predicates.push(self.parse_predicate()?);
+        }
+        Ok(Step { axis, test, predicates })
+    }
+
+    fn parse_predicate(&mut self) -> Result<Predicate, XPathError> {
+        self.bump(); // consume '['
+        self.skip_ws();
+        let pred = if self.peek().map_or(false, |c| c.is_ascii_digit()) {
+            let mut n = 0usize;
+            while let Some(c) = self.peek() {
+                if !c.is_ascii_digit() {
+                    break;
+                }
+                n = n * 10 + (c - b'0') as usize;
+                self.bump();
+            }
+            Predicate::Position(n)
+        } else if self.peek() == Some(b'@') {
+            self.bump();
+            let name = self.parse_name();
+            self.skip_ws();
+            if self.peek() == Some(b'=') {
+                self.bump();
+                let value = self.parse_string()?;
+                Predicate::AttrEquals(name, value)
+            } else {
+                Predicate::AttrExists(name)
+            }
+        } else if self.starts_with("contains(text()") {
+            self.pos += "contains(text()".len();
+            self.skip_ws();
+            if self.peek() != Some(b',') {
+                return Err(self.err("expected ',' in contains()"));
+            }
+            self.bump();
+            self.skip_ws();
+            let needle = self.parse_string()?;
+            self.skip_ws();
+            if self.peek() != Some(b')') {
+                return Err(self.err("expected ')' to close contains()"));
+            }
+            self.bump();
+            Predicate::ContainsText(needle)
+        } else {
+            return Err(self.err("unsupported predicate"));
+        };
+        self.skip_ws();
+        if self.bump() != Some(b']') {
+            return Err(self.err("expected ']' to close predicate"));
+        }
+        Ok(pred)
+    }
+
+    fn parse_name(&mut self) -> String {
+        let start = self.pos;
+        while let Some(c) = self.peek() {
+            if c.is_ascii_alphanumeric() || matches!(c, b'-' | b'_' | b'.' | b':') {
+                self.bump();
+            } else {
+                break;
+            }
+        }
+        String::from_utf8_lossy(&self.input[start..self.pos]).into_owned()
+    }
+
+    fn parse_string(&mut self) -> Result<String, XPathError> {
+        let quote = match self.bump() {
+            Some(c @ b'\'') | Some(c @ b'"') => c,
+            _ => return Err(self.err("expected quoted string literal")),
+        };
+        let start = self.pos;
+        while let Some(c) = self.peek() {
+            if c == quote {
+                let s = String::from_utf8_lossy(&self.input[start..self.pos]).into_owned();
+                self.bump();
+                return Ok(s);
+            }
+            self.bump();
+        }
+        Err(self.err("unterminated string literal"))
+    }
+
+    fn skip_ws(&mut self) {
+        while self.peek().map_or(false, |c| c.is_ascii_whitespace()) {
+            self.bump();
+        }
+    }
+
+    fn starts_with(&self, s: &str) -> bool {
+        self.input[self.pos..].starts_with(s.as_bytes())
+    }
+}
+
+fn test_matches(node: &DOMNode, test: &NodeTest) -> bool {
+    if node.node_type() != NodeType::Element {
+        return false;
+    }
+    match test {
+        NodeTest::Wildcard => true,
+        NodeTest::Name(name) => node
+            .tag()
+            .map_or(false, |t| t.eq_ignore_ascii_case(name)),
+    }
+}
+
+fn apply_predicates(nodes: Vec<DOMNode>, predicates: &[Predicate]) -> Vec<DOMNode> {
+    let mut current = nodes;
+    for pred in predicates {
+        current = match pred {
+            Predicate::Position(n) => current
+                .into_iter()
+                .enumerate()
+                .filter(|(i, _)| i + 1 == *n)
+                .map(|(_, node)| node)
+                .collect(),
+            Predicate::AttrExists(name) => current
+                .into_iter()
+                .filter(|node| node.get_attribute(name).is_some())
+                .collect(),
+            Predicate::AttrEquals(name, value) => current
+                .into_iter()
+                .filter(|node| node.get_attribute(name).as_deref() == Some(value.as_str()))
+                .collect(),
+            Predicate::ContainsText(needle) => current
+                .into_iter()
+                .filter(|node| node.inner_text().map_or(false, |t| t.contains(needle)))
+                .collect(),
+        };
+    }
+    current
+}
+
+/// Group nodes by their parent, preserving document order of both the groups
+/// and the nodes within each group.
+fn group_by_parent(nodes: Vec<DOMNode>) -> Vec<Vec<DOMNode>> {
+    let mut order: Vec<usize> = Vec::new();
+    let mut groups: std::collections::HashMap<usize, Vec<DOMNode>> =
+        std::collections::HashMap::new();
+    for node in nodes {
+        let parent_id = node.parent().map_or(0, |p| p.node_id());
+        if !groups.contains_key(&parent_id) {
+            order.push(parent_id);
+        }
+        groups.entry(parent_id).or_default().push(node);
+    }
+    order.into_iter().map(|id| groups.remove(&id).unwrap()).collect()
+}
+
+/// Pre-order descendant element nodes in document order.
+fn descendants(node: &DOMNode) -> Vec<DOMNode> {
+    let mut out = Vec::new();
+    for child in node.child_nodes() {
+        if child.node_type() != NodeType::Element {
+            continue;
+        }
+        let sub = descendants(&child);
+        out.push(child);
+        out.extend(sub);
+    }
+    out
+}
+
+/// Dedupe a node-set by identity, preserving document order.
+fn dedupe(nodes: Vec<DOMNode>) -> Vec<DOMNode> {
+    let mut seen = std::collections::HashSet::new();
+    nodes.into_iter().filter(|n| seen.insert(n.node_id())).collect()
+}
+
+/// Top-most ancestor of a node (the document root).
+fn document_root(node: &DOMNode) -> DOMNode {
+    let mut current = node.clone();
+    while let Some(parent) = current.parent() {
+        current = parent;
+    }
+    current
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse::html::HTMLTree;
+
+    const HTML: &str = r#"<body><main id="foo">
+        <p class="a">one</p><p class="b">two</p>
+        <a href="u">link</a></main></body>"#;
+
+    fn nodeset(result: XPathResult) -> Vec<DOMNode> {
+        match result {
+            XPathResult::NodeSet(n) => n,
+            _ => panic!("expected node set"),
+        }
+    }
+
+    #[test]
+    fn descendant_and_name_test() {
+        let tree = HTMLTree::from(HTML);
+        let body = tree.body().unwrap();
+        assert_eq!(nodeset(body.evaluate("//p").unwrap()).len(), 2);
+    }
+
+    #[test]
+    fn positional_and_attribute_predicates() {
+        let tree = HTMLTree::from(HTML);
+        let body = tree.body().unwrap();
+        let first = nodeset(body.evaluate("//p[1]").unwrap());
+        assert_eq!(first.len(), 1);
+        assert_eq!(first[0].inner_text().as_deref(), Some("one"));
+        assert_eq!(nodeset(body.evaluate("//p[@class='b']").unwrap()).len(), 1);
+    }
+
+    #[test]
+    fn positional_predicate_is_per_parent() {
+        let html = "<body><ul><li>a1</li><li>a2</li></ul>\
+                    <ul><li>b1</li><li>b2</li></ul></body>";
+        let tree = HTMLTree::from(html);
+        let body = tree.body().unwrap();
+        let firsts = nodeset(body.evaluate("//li[1]").unwrap());
+        assert_eq!(firsts.len(), 2);
+        assert_eq!(firsts[0].inner_text().as_deref(), Some("a1"));
+        assert_eq!(firsts[1].inner_text().as_deref(), Some("b1"));
+    }
+
+    #[test]
+    fn attribute_axis_is_terminal() {
+        let tree = HTMLTree::from(HTML);
+        let body = tree.body().unwrap();
+        match body.evaluate("//a/@href").unwrap() {
+            XPathResult::StringSet(s) => assert_eq!(s, vec!["u".to_string()]),
+            _ => panic!("expected string set"),
+        }
+    }
+
+    #[test]
+    fn attribute_axis_collects_every_match() {
+        let html = "<body><a href=\"one\">x</a><a href=\"two\">y</a></body>";
+        let tree = HTMLTree::from(html);
+        let body = tree.body().unwrap();
+        match body.evaluate("//a/@href").unwrap() {
+            XPathResult::StringSet(s) => {
+                assert_eq!(s, vec!["one".to_string(), "two".to_string()]);
+            }
+            _ => panic!("expected string set"),
+        }
+    }
+}